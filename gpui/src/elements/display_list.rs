@@ -0,0 +1,390 @@
+use crate::{
+    color::ColorU,
+    geometry::{rect::RectF, vector::Vector2F},
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+/// A single retained drawing command.
+///
+/// Primitives are plain data rather than imperative backend calls, so a layer
+/// whose commands are unchanged between frames can be diffed and skipped.
+#[derive(Clone, Debug)]
+pub enum Primitive {
+    Quad {
+        bounds: RectF,
+        background: ColorU,
+    },
+    GlyphRun {
+        origin: Vector2F,
+        font_size: f32,
+        color: ColorU,
+        glyphs: Vec<Glyph>,
+    },
+    Image {
+        bounds: RectF,
+        data: Arc<ImageData>,
+    },
+}
+
+/// A positioned glyph within a [`Primitive::GlyphRun`].
+#[derive(Clone, Debug)]
+pub struct Glyph {
+    pub id: u32,
+    pub offset: Vector2F,
+}
+
+/// Raw RGBA image pixels referenced by a [`Primitive::Image`].
+#[derive(Debug)]
+pub struct ImageData {
+    pub size: Vector2F,
+    pub pixels: Vec<u8>,
+}
+
+/// A clipped group of primitives.
+///
+/// Layers form a stack: `push_layer` opens one bounded by a clip rect and
+/// `pop_layer` closes it. Each layer tracks a rolling content hash so the
+/// renderer can diff it against the previous frame and skip it when unchanged.
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub clip: Option<RectF>,
+    pub primitives: Vec<Primitive>,
+    hash: u64,
+}
+
+impl Layer {
+    fn new(clip: Option<RectF>) -> Self {
+        let mut hasher = DefaultHasher::new();
+        hash_rect(clip, &mut hasher);
+        Self {
+            clip,
+            primitives: Vec::new(),
+            hash: hasher.finish(),
+        }
+    }
+
+    fn push(&mut self, primitive: Primitive) {
+        let mut hasher = DefaultHasher::new();
+        self.hash.hash(&mut hasher);
+        hash_primitive(&primitive, &mut hasher);
+        self.hash = hasher.finish();
+        self.primitives.push(primitive);
+    }
+
+    /// A content hash identifying everything drawn into this layer. Two layers
+    /// with equal hashes produce identical pixels and can be skipped.
+    pub fn content_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// The axis-aligned region this layer can touch: the union of its quad and
+    /// image bounds, tightened by the clip rect. Glyph runs carry no bounds and
+    /// are not counted. `None` means the layer has no quad or image inside its
+    /// clip, so its extent is unknown. Used to decide whether a changed layer
+    /// forces an otherwise-unchanged layer above it to be re-composited.
+    pub fn bounds(&self) -> Option<RectF> {
+        let mut union: Option<RectF> = None;
+        for primitive in &self.primitives {
+            let bounds = match primitive {
+                Primitive::Quad { bounds, .. } => *bounds,
+                Primitive::GlyphRun { .. } => continue,
+                Primitive::Image { bounds, .. } => *bounds,
+            };
+            union = Some(match union {
+                Some(current) => current.union_rect(bounds),
+                None => bounds,
+            });
+        }
+        match (union, self.clip) {
+            (Some(union), Some(clip)) => union.intersection(clip),
+            (union, _) => union,
+        }
+    }
+}
+
+/// A retained buffer of drawing commands an element's `paint` records into.
+///
+/// Rather than issuing backend calls immediately, paint code records primitives
+/// here and a [`Renderer`] replays them into a framebuffer. Because the list is
+/// data, unchanged layers can be diffed away between frames.
+#[derive(Clone, Debug, Default)]
+pub struct DisplayList {
+    layers: Vec<Layer>,
+    stack: Vec<usize>,
+}
+
+impl DisplayList {
+    pub fn new() -> Self {
+        let mut list = Self::default();
+        list.push_layer(None);
+        list
+    }
+
+    /// Open a new clipped layer and make it the current recording target.
+    pub fn push_layer(&mut self, clip: Option<RectF>) {
+        self.layers.push(Layer::new(clip));
+        self.stack.push(self.layers.len() - 1);
+    }
+
+    /// Close the current layer, returning to its parent.
+    pub fn pop_layer(&mut self) {
+        self.stack.pop();
+    }
+
+    pub fn push_quad(&mut self, bounds: RectF, background: ColorU) {
+        self.record(Primitive::Quad { bounds, background });
+    }
+
+    pub fn push_glyphs(
+        &mut self,
+        origin: Vector2F,
+        font_size: f32,
+        color: ColorU,
+        glyphs: Vec<Glyph>,
+    ) {
+        self.record(Primitive::GlyphRun {
+            origin,
+            font_size,
+            color,
+            glyphs,
+        });
+    }
+
+    pub fn push_image(&mut self, bounds: RectF, data: Arc<ImageData>) {
+        self.record(Primitive::Image { bounds, data });
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    fn record(&mut self, primitive: Primitive) {
+        let index = self.stack.last().copied().unwrap_or(0);
+        self.layers[index].push(primitive);
+    }
+}
+
+/// An RGBA8 framebuffer the CPU renderer rasterizes into.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        }
+    }
+
+    fn fill_rect(&mut self, bounds: RectF, clip: Option<RectF>, color: ColorU) {
+        let zero = RectF::new(Vector2F::zero(), Vector2F::zero());
+        let bounds = match clip {
+            Some(clip) => bounds.intersection(clip).unwrap_or(zero),
+            None => bounds,
+        };
+        let x0 = (bounds.origin().x().max(0.0) as usize).min(self.width);
+        let y0 = (bounds.origin().y().max(0.0) as usize).min(self.height);
+        let x1 = ((bounds.origin().x() + bounds.size().x()).max(0.0) as usize).min(self.width);
+        let y1 = ((bounds.origin().y() + bounds.size().y()).max(0.0) as usize).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let offset = (y * self.width + x) * 4;
+                self.pixels[offset] = color.r;
+                self.pixels[offset + 1] = color.g;
+                self.pixels[offset + 2] = color.b;
+                self.pixels[offset + 3] = color.a;
+            }
+        }
+    }
+}
+
+/// Rasterizes a [`DisplayList`] into a [`Frame`] on the CPU.
+///
+/// The renderer keeps the previous frame's per-layer content hashes and leaves
+/// a layer whose hash is unchanged untouched in the framebuffer, unless it
+/// overlaps a lower layer re-filled this frame, so a mostly-static tree
+/// re-rasterizes almost nothing. A GPU backend (instanced
+/// quad batching, glyph-atlas uploads) is intended to consume the same list in
+/// a later change; it is not implemented here.
+pub struct Renderer {
+    frame: Frame,
+    previous: Vec<u64>,
+}
+
+impl Renderer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            frame: Frame::new(width, height),
+            previous: Vec::new(),
+        }
+    }
+
+    /// Replay `list`, re-rasterizing only the layers whose content changed —
+    /// plus any layer that overlaps a lower layer that was re-filled this
+    /// frame, so compositing order stays correct — and return the framebuffer.
+    pub fn render(&mut self, list: &DisplayList) -> &Frame {
+        let mut redrawn: Vec<RectF> = Vec::new();
+        for (index, layer) in list.layers().iter().enumerate() {
+            let changed = self.previous.get(index) != Some(&layer.content_hash());
+            // An unchanged layer still has to be re-composited if a lower layer
+            // under it was re-filled, otherwise stale pixels from the lower
+            // layer would show through.
+            let overlaps_redraw = match layer.bounds() {
+                Some(bounds) => redrawn
+                    .iter()
+                    .any(|lower| lower.intersection(bounds).is_some()),
+                // Unknown extent: be conservative once anything below changed.
+                None => !redrawn.is_empty(),
+            };
+            if !changed && !overlaps_redraw {
+                continue;
+            }
+            for primitive in &layer.primitives {
+                if let Primitive::Quad { bounds, background } = primitive {
+                    self.frame.fill_rect(*bounds, layer.clip, *background);
+                }
+                // Glyph and image primitives are recorded but not yet
+                // rasterized by the CPU path.
+            }
+            if let Some(bounds) = layer.bounds() {
+                redrawn.push(bounds);
+            }
+        }
+        self.previous = list.layers().iter().map(Layer::content_hash).collect();
+        &self.frame
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+}
+
+fn hash_color(color: ColorU, hasher: &mut DefaultHasher) {
+    color.r.hash(hasher);
+    color.g.hash(hasher);
+    color.b.hash(hasher);
+    color.a.hash(hasher);
+}
+
+fn hash_rect(rect: Option<RectF>, hasher: &mut DefaultHasher) {
+    if let Some(rect) = rect {
+        for value in [
+            rect.origin().x(),
+            rect.origin().y(),
+            rect.size().x(),
+            rect.size().y(),
+        ] {
+            value.to_bits().hash(hasher);
+        }
+    } else {
+        0u8.hash(hasher);
+    }
+}
+
+fn hash_primitive(primitive: &Primitive, hasher: &mut DefaultHasher) {
+    match primitive {
+        Primitive::Quad { bounds, background } => {
+            0u8.hash(hasher);
+            hash_rect(Some(*bounds), hasher);
+            hash_color(*background, hasher);
+        }
+        Primitive::GlyphRun {
+            origin,
+            font_size,
+            color,
+            glyphs,
+        } => {
+            1u8.hash(hasher);
+            origin.x().to_bits().hash(hasher);
+            origin.y().to_bits().hash(hasher);
+            font_size.to_bits().hash(hasher);
+            hash_color(*color, hasher);
+            for glyph in glyphs {
+                glyph.id.hash(hasher);
+                glyph.offset.x().to_bits().hash(hasher);
+                glyph.offset.y().to_bits().hash(hasher);
+            }
+        }
+        Primitive::Image { bounds, data } => {
+            2u8.hash(hasher);
+            hash_rect(Some(*bounds), hasher);
+            (Arc::as_ptr(data) as usize).hash(hasher);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::vector::vec2f;
+
+    fn color(r: u8, g: u8, b: u8) -> ColorU {
+        ColorU { r, g, b, a: 255 }
+    }
+
+    #[test]
+    fn fills_quad_into_framebuffer() {
+        let mut list = DisplayList::new();
+        list.push_quad(
+            RectF::new(vec2f(1.0, 1.0), vec2f(2.0, 2.0)),
+            color(255, 0, 0),
+        );
+
+        let mut renderer = Renderer::new(4, 4);
+        let frame = renderer.render(&list);
+
+        // A pixel inside the quad is red; a pixel outside is untouched.
+        let inside = (1 * 4 + 1) * 4;
+        assert_eq!(&frame.pixels[inside..inside + 4], &[255, 0, 0, 255]);
+        let outside = (0 * 4 + 0) * 4;
+        assert_eq!(&frame.pixels[outside..outside + 4], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn unchanged_layer_keeps_the_same_content_hash() {
+        let build = || {
+            let mut list = DisplayList::new();
+            list.push_quad(RectF::new(vec2f(0.0, 0.0), vec2f(1.0, 1.0)), color(1, 2, 3));
+            list
+        };
+        assert_eq!(
+            build().layers()[0].content_hash(),
+            build().layers()[0].content_hash()
+        );
+    }
+
+    // A rect R painted by layer0 (below) then layer1 (above, blue wins). When
+    // layer0 changes but layer1 does not, the unchanged upper layer must still
+    // be re-composited so blue — not the new layer0 color — shows through.
+    #[test]
+    fn changed_lower_layer_forces_overlapping_upper_layer_redraw() {
+        let rect = RectF::new(vec2f(0.0, 0.0), vec2f(2.0, 2.0));
+        let build = |lower: ColorU| {
+            let mut list = DisplayList::new();
+            list.layers[0].push(Primitive::Quad {
+                bounds: rect,
+                background: lower,
+            });
+            list.push_layer(None);
+            list.push_quad(rect, color(0, 0, 255));
+            list.pop_layer();
+            list
+        };
+
+        let mut renderer = Renderer::new(2, 2);
+        renderer.render(&build(color(255, 0, 0)));
+        let frame = renderer.render(&build(color(0, 255, 0)));
+
+        // The pixel stays blue; the green lower layer does not bleed through.
+        assert_eq!(&frame.pixels[0..4], &[0, 0, 255, 255]);
+    }
+}
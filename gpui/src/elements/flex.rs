@@ -0,0 +1,274 @@
+use crate::{
+    elements::{
+        display_list::DisplayList,
+        new::{Element, ElementBox},
+    },
+    geometry::{
+        rect::RectF,
+        vector::{vec2f, Vector2F},
+    },
+    AfterLayoutContext, DebugContext, Event, EventContext, LayoutContext, PaintContext,
+    SizeConstraint,
+};
+use serde_json::json;
+
+/// The main axis a [`Flex`] lays its children out along.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+impl Axis {
+    /// The component of `v` that runs along this axis.
+    fn main(&self, v: Vector2F) -> f32 {
+        match self {
+            Axis::Horizontal => v.x(),
+            Axis::Vertical => v.y(),
+        }
+    }
+
+    /// The component of `v` that runs across this axis.
+    fn cross(&self, v: Vector2F) -> f32 {
+        match self {
+            Axis::Horizontal => v.y(),
+            Axis::Vertical => v.x(),
+        }
+    }
+
+    /// Build a vector from its main- and cross-axis components.
+    fn vec(&self, main: f32, cross: f32) -> Vector2F {
+        match self {
+            Axis::Horizontal => vec2f(main, cross),
+            Axis::Vertical => vec2f(cross, main),
+        }
+    }
+}
+
+struct FlexChild {
+    flex: Option<f32>,
+    element: ElementBox,
+}
+
+/// A multi-child container that lays its children out in a row or column.
+///
+/// Inflexible children are measured at their natural size first; whatever
+/// main-axis space is left over is then split among the flexible children in
+/// proportion to their `flex` weights.
+pub struct Flex {
+    axis: Axis,
+    children: Vec<FlexChild>,
+}
+
+impl Flex {
+    pub fn new(axis: Axis) -> Self {
+        Self {
+            axis,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn row() -> Self {
+        Self::new(Axis::Horizontal)
+    }
+
+    pub fn column() -> Self {
+        Self::new(Axis::Vertical)
+    }
+
+    /// Add an inflexible child, measured at its natural size.
+    pub fn with_child(mut self, child: ElementBox) -> Self {
+        self.children.push(FlexChild {
+            flex: None,
+            element: child,
+        });
+        self
+    }
+
+    /// Add a flexible child that receives a share of the leftover main-axis
+    /// space proportional to `flex`.
+    pub fn with_flex_child(mut self, flex: f32, child: ElementBox) -> Self {
+        self.children.push(FlexChild {
+            flex: Some(flex),
+            element: child,
+        });
+        self
+    }
+}
+
+impl Element for Flex {
+    type LayoutState = Vec<RectF>;
+    type PaintState = ();
+
+    fn layout(
+        &mut self,
+        constraint: SizeConstraint,
+        ctx: &mut LayoutContext,
+    ) -> (Vector2F, Self::LayoutState) {
+        let cross_min = self.axis.cross(constraint.min);
+        let cross_max = self.axis.cross(constraint.max);
+
+        // First pass: measure inflexible children at their natural size and
+        // tally the main-axis space they consume.
+        let mut sizes = vec![Vector2F::zero(); self.children.len()];
+        let mut fixed_main = 0.0;
+        for (ix, child) in self.children.iter_mut().enumerate() {
+            if child.flex.is_some() {
+                continue;
+            }
+            let child_constraint = SizeConstraint::new(
+                self.axis.vec(0.0, cross_min),
+                self.axis.vec(self.axis.main(constraint.max), cross_max),
+            );
+            let size = child.element.layout(child_constraint, ctx);
+            fixed_main += self.axis.main(size);
+            sizes[ix] = size;
+        }
+
+        // Second pass: distribute the remaining space to flexible children in
+        // proportion to their weights, tightening the main axis to the share.
+        let remaining = (self.axis.main(constraint.max) - fixed_main).max(0.0);
+        let weights: Vec<f32> = self.children.iter().filter_map(|c| c.flex).collect();
+        let mut allotments = distribute_flex(remaining, &weights).into_iter();
+        for (ix, child) in self.children.iter_mut().enumerate() {
+            if child.flex.is_some() {
+                let main = allotments.next().unwrap_or(0.0);
+                let child_constraint = SizeConstraint::new(
+                    self.axis.vec(main, cross_min),
+                    self.axis.vec(main, cross_max),
+                );
+                sizes[ix] = child.element.layout(child_constraint, ctx);
+            }
+        }
+
+        // Resolve each child's origin along the main axis and record its rect
+        // so paint and debug can place each child. Event dispatch reaches a
+        // child through the bounds it was painted at.
+        let mut main_offset = 0.0;
+        let mut cross_extent: f32 = 0.0;
+        let mut child_rects = Vec::with_capacity(self.children.len());
+        for size in &sizes {
+            let origin = self.axis.vec(main_offset, 0.0);
+            child_rects.push(RectF::new(origin, *size));
+            main_offset += self.axis.main(*size);
+            cross_extent = cross_extent.max(self.axis.cross(*size));
+        }
+
+        let size = self.axis.vec(main_offset, cross_extent);
+        (size, child_rects)
+    }
+
+    fn after_layout(
+        &mut self,
+        _size: Vector2F,
+        _layout: &mut Self::LayoutState,
+        ctx: &mut AfterLayoutContext,
+    ) {
+        for child in &mut self.children {
+            child.element.after_layout(ctx);
+        }
+    }
+
+    fn paint(
+        &mut self,
+        bounds: RectF,
+        layout: &mut Self::LayoutState,
+        ctx: &mut PaintContext,
+        list: &mut DisplayList,
+    ) -> Self::PaintState {
+        // Clip children to the container and record their primitives into the
+        // retained display list.
+        list.push_layer(Some(bounds));
+        for (child, rect) in self.children.iter_mut().zip(layout.iter()) {
+            child.element.paint(bounds.origin() + rect.origin(), ctx, list);
+        }
+        list.pop_layer();
+    }
+
+    fn dispatch_event(
+        &mut self,
+        event: &Event,
+        _bounds: RectF,
+        _layout: &mut Self::LayoutState,
+        _paint: &mut Self::PaintState,
+        ctx: &mut EventContext,
+    ) -> bool {
+        let mut handled = false;
+        for child in &mut self.children {
+            handled = child.element.dispatch_event(event, ctx) || handled;
+        }
+        handled
+    }
+
+    fn children(&self) -> Vec<&ElementBox> {
+        self.children.iter().map(|child| &child.element).collect()
+    }
+
+    fn debug(
+        &self,
+        bounds: RectF,
+        layout: &Self::LayoutState,
+        _paint: &Self::PaintState,
+        ctx: &DebugContext,
+    ) -> serde_json::Value {
+        json!({
+            "type": "Flex",
+            "axis": format!("{:?}", self.axis),
+            "bounds": rect_json(bounds),
+            "children": self.children
+                .iter()
+                .zip(layout.iter())
+                .map(|(child, rect)| json!({
+                    "rect": rect_json(RectF::new(bounds.origin() + rect.origin(), rect.size())),
+                    "element": child.element.debug(ctx),
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn rect_json(rect: RectF) -> serde_json::Value {
+    json!({
+        "origin": [rect.origin().x(), rect.origin().y()],
+        "size": [rect.size().x(), rect.size().y()],
+    })
+}
+
+/// Split `available` main-axis space across flexible children in proportion to
+/// their `weights`. When the weights sum to zero the flexible children receive
+/// nothing, matching the inflexible-only case.
+fn distribute_flex(available: f32, weights: &[f32]) -> Vec<f32> {
+    let total: f32 = weights.iter().sum();
+    weights
+        .iter()
+        .map(|weight| {
+            if total > 0.0 {
+                available.max(0.0) * weight / total
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distributes_space_proportional_to_weights() {
+        assert_eq!(distribute_flex(90.0, &[1.0, 2.0]), vec![30.0, 60.0]);
+        assert_eq!(distribute_flex(100.0, &[1.0, 1.0, 2.0]), vec![25.0, 25.0, 50.0]);
+    }
+
+    #[test]
+    fn zero_total_flex_allots_nothing() {
+        assert_eq!(distribute_flex(100.0, &[0.0, 0.0]), vec![0.0, 0.0]);
+        assert_eq!(distribute_flex(100.0, &[]), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn negative_remaining_is_clamped_to_zero() {
+        assert_eq!(distribute_flex(-10.0, &[1.0, 1.0]), vec![0.0, 0.0]);
+    }
+}
@@ -1,21 +1,33 @@
 use crate::{
+    elements::display_list::DisplayList,
     geometry::{rect::RectF, vector::Vector2F},
     json, AfterLayoutContext, DebugContext, Event, EventContext, LayoutContext, PaintContext,
     SizeConstraint,
 };
 use core::panic;
 use replace_with::replace_with_or_abort;
-use std::{any::Any, borrow::Cow};
+use std::{
+    any::{Any, TypeId},
+    borrow::Cow,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
 
 trait AnyElement {
     fn layout(&mut self, constraint: SizeConstraint, ctx: &mut LayoutContext) -> Vector2F;
     fn after_layout(&mut self, _: &mut AfterLayoutContext) {}
-    fn paint(&mut self, origin: Vector2F, ctx: &mut PaintContext);
+    fn paint(&mut self, origin: Vector2F, ctx: &mut PaintContext, list: &mut DisplayList);
     fn dispatch_event(&mut self, event: &Event, ctx: &mut EventContext) -> bool;
     fn debug(&self, ctx: &DebugContext) -> serde_json::Value;
 
     fn size(&self) -> Vector2F;
     fn metadata(&self) -> Option<&dyn Any>;
+    fn bounds(&self) -> Option<RectF>;
+    fn children(&self) -> Vec<&ElementBox>;
+
+    /// Drop the memoized layout key so the next `layout` recomputes instead of
+    /// returning a stale cached size.
+    fn invalidate(&mut self);
 }
 
 pub trait Element {
@@ -40,6 +52,7 @@ pub trait Element {
         bounds: RectF,
         layout: &mut Self::LayoutState,
         ctx: &mut PaintContext,
+        list: &mut DisplayList,
     ) -> Self::PaintState;
 
     fn dispatch_event(
@@ -55,6 +68,24 @@ pub trait Element {
         None
     }
 
+    /// A content hash used to memoize layout across frames. When the element's
+    /// layout inputs can be cheaply digested into a single value, returning
+    /// `Some` lets `Lifecycle` skip re-running `layout` while neither those
+    /// inputs nor the incoming `SizeConstraint` have changed. Returning `None`
+    /// (the default) opts out and keeps the eager per-frame behavior.
+    fn cache_key(&self) -> Option<u64> {
+        None
+    }
+
+    /// The child elements this element lays out, if any. Container elements
+    /// override this so the inspector can walk the whole tree; leaf elements
+    /// keep the default empty list. Returning a `Vec` of borrows lets
+    /// containers that wrap their children (e.g. `Flex`) project out the inner
+    /// `ElementBox`es without storing a bare slice.
+    fn children(&self) -> Vec<&ElementBox> {
+        Vec::new()
+    }
+
     fn debug(
         &self,
         bounds: RectF,
@@ -70,6 +101,8 @@ pub trait Element {
         ElementBox {
             name: None,
             element: Box::new(Lifecycle::Init { element: self }),
+            needs_layout: true,
+            needs_paint: true,
         }
     }
 
@@ -80,6 +113,8 @@ pub trait Element {
         ElementBox {
             name: Some(name.into()),
             element: Box::new(Lifecycle::Init { element: self }),
+            needs_layout: true,
+            needs_paint: true,
         }
     }
 }
@@ -92,21 +127,78 @@ pub enum Lifecycle<T: Element> {
         element: T,
         size: Vector2F,
         layout: T::LayoutState,
+        cache_key: Option<u64>,
     },
     PostPaint {
         element: T,
         bounds: RectF,
         layout: T::LayoutState,
         paint: T::PaintState,
+        cache_key: Option<u64>,
     },
 }
 pub struct ElementBox {
     name: Option<Cow<'static, str>>,
     element: Box<dyn AnyElement>,
+    needs_layout: bool,
+    needs_paint: bool,
+}
+
+impl<T: Element> Lifecycle<T> {
+    /// Digest the element's `cache_key` together with the constraint bounds
+    /// into a single memoization key: one hasher absorbs each contributing
+    /// field in turn. Returns `None` when the
+    /// element opts out of layout caching.
+    fn combined_cache_key(element: &T, constraint: SizeConstraint) -> Option<u64> {
+        element.cache_key().map(|key| {
+            let mut hasher = DefaultHasher::new();
+            key.hash(&mut hasher);
+            for value in [
+                constraint.min.x(),
+                constraint.min.y(),
+                constraint.max.x(),
+                constraint.max.y(),
+            ] {
+                value.to_bits().hash(&mut hasher);
+            }
+            hasher.finish()
+        })
+    }
 }
 
 impl<T: Element> AnyElement for Lifecycle<T> {
     fn layout(&mut self, constraint: SizeConstraint, ctx: &mut LayoutContext) -> Vector2F {
+        let combined_hash = match self {
+            Lifecycle::Init { element }
+            | Lifecycle::PostLayout { element, .. }
+            | Lifecycle::PostPaint { element, .. } => {
+                Self::combined_cache_key(element, constraint)
+            }
+        };
+
+        // Fast path: the element is already laid out and nothing feeding its
+        // layout changed, so reuse the cached size and `LayoutState`. This also
+        // fires from `PostPaint`, since in a steady `layout -> paint` frame loop
+        // the element is in `PostPaint` by the time `layout` is next called.
+        let cached = match &*self {
+            Lifecycle::PostLayout {
+                cache_key: Some(key),
+                size,
+                ..
+            } => Some((*key, *size)),
+            Lifecycle::PostPaint {
+                cache_key: Some(key),
+                bounds,
+                ..
+            } => Some((*key, bounds.size())),
+            _ => None,
+        };
+        if let (Some(hash), Some((cached_hash, size))) = (combined_hash, cached) {
+            if hash == cached_hash {
+                return size;
+            }
+        }
+
         let mut result = None;
         replace_with_or_abort(self, |me| match me {
             Lifecycle::Init { mut element }
@@ -121,6 +213,7 @@ impl<T: Element> AnyElement for Lifecycle<T> {
                     element,
                     size,
                     layout,
+                    cache_key: combined_hash,
                 }
             }
         });
@@ -132,33 +225,57 @@ impl<T: Element> AnyElement for Lifecycle<T> {
             element,
             size,
             layout,
+            ..
         } = self
         {
             element.after_layout(*size, layout, ctx);
-        } else {
-            panic!("invalid element lifecycle state");
         }
+        // Outside `PostLayout` (not laid out yet, or already painted) this is a
+        // no-op rather than an abort, so a stale element can be re-driven
+        // through `layout` first.
     }
 
-    fn paint(&mut self, origin: Vector2F, ctx: &mut PaintContext) {
-        replace_with_or_abort(self, |me| {
-            if let Lifecycle::PostLayout {
+    fn paint(&mut self, origin: Vector2F, ctx: &mut PaintContext, list: &mut DisplayList) {
+        replace_with_or_abort(self, |me| match me {
+            Lifecycle::PostLayout {
                 mut element,
                 size,
                 mut layout,
-            } = me
-            {
+                cache_key,
+            } => {
                 let bounds = RectF::new(origin, size);
-                let paint = element.paint(bounds, &mut layout, ctx);
+                let paint = element.paint(bounds, &mut layout, ctx, list);
                 Lifecycle::PostPaint {
                     element,
                     bounds,
                     layout,
                     paint,
+                    cache_key,
                 }
-            } else {
-                panic!("invalid element lifecycle state");
             }
+            // Already painted: re-paint at the (possibly new) origin, reusing
+            // the cached layout. This supports re-inserting a painted element
+            // mid-frame without first re-running layout.
+            Lifecycle::PostPaint {
+                mut element,
+                bounds,
+                mut layout,
+                cache_key,
+                ..
+            } => {
+                let bounds = RectF::new(origin, bounds.size());
+                let paint = element.paint(bounds, &mut layout, ctx, list);
+                Lifecycle::PostPaint {
+                    element,
+                    bounds,
+                    layout,
+                    paint,
+                    cache_key,
+                }
+            }
+            // Never laid out: painting is impossible without a constraint, so
+            // stay stale and let the caller re-run `layout` on demand.
+            init @ Lifecycle::Init { .. } => init,
         });
     }
 
@@ -168,17 +285,20 @@ impl<T: Element> AnyElement for Lifecycle<T> {
             bounds,
             layout,
             paint,
+            ..
         } = self
         {
             element.dispatch_event(event, *bounds, layout, paint, ctx)
         } else {
-            panic!("invalid element lifecycle state");
+            // The element hasn't been painted this frame, so it occupies no
+            // bounds an event could hit; treat it as unhandled.
+            false
         }
     }
 
     fn size(&self) -> Vector2F {
         match self {
-            Lifecycle::Init { .. } => panic!("invalid element lifecycle state"),
+            Lifecycle::Init { .. } => Vector2F::zero(),
             Lifecycle::PostLayout { size, .. } => *size,
             Lifecycle::PostPaint { bounds, .. } => bounds.size(),
         }
@@ -192,6 +312,29 @@ impl<T: Element> AnyElement for Lifecycle<T> {
         }
     }
 
+    fn bounds(&self) -> Option<RectF> {
+        match self {
+            Lifecycle::PostPaint { bounds, .. } => Some(*bounds),
+            _ => None,
+        }
+    }
+
+    fn children(&self) -> Vec<&ElementBox> {
+        match self {
+            Lifecycle::Init { element }
+            | Lifecycle::PostLayout { element, .. }
+            | Lifecycle::PostPaint { element, .. } => element.children(),
+        }
+    }
+
+    fn invalidate(&mut self) {
+        match self {
+            Lifecycle::Init { .. } => {}
+            Lifecycle::PostLayout { cache_key, .. }
+            | Lifecycle::PostPaint { cache_key, .. } => *cache_key = None,
+        }
+    }
+
     fn debug(&self, ctx: &DebugContext) -> serde_json::Value {
         match self {
             Lifecycle::PostPaint {
@@ -199,26 +342,66 @@ impl<T: Element> AnyElement for Lifecycle<T> {
                 bounds,
                 layout,
                 paint,
+                ..
             } => element.debug(*bounds, layout, paint, ctx),
-            _ => panic!("invalid element lifecycle state"),
+            // Nothing painted yet, so there is no resolved state to serialize.
+            _ => serde_json::Value::Null,
         }
     }
 }
 
 impl ElementBox {
+    /// Mark this element (and therefore its subtree) stale: `paint` is skipped
+    /// until `layout` has run again, and `paint` does not re-run `layout`
+    /// itself. Call this when an ancestor is re-laid-out or the element's
+    /// content changed mid-frame.
+    pub fn invalidate(&mut self) {
+        self.needs_layout = true;
+        self.needs_paint = true;
+        // Drop the memoized layout key so the chunk0-1 fast path can't hand
+        // back a stale size on the next `layout`.
+        self.element.invalidate();
+    }
+
+    /// Whether this element still needs to be laid out before it can paint.
+    pub fn needs_layout(&self) -> bool {
+        self.needs_layout
+    }
+
+    /// Whether this element still needs to be painted before it can dispatch
+    /// events.
+    pub fn needs_paint(&self) -> bool {
+        self.needs_paint
+    }
+
     pub fn layout(&mut self, constraint: SizeConstraint, ctx: &mut LayoutContext) -> Vector2F {
-        self.element.layout(constraint, ctx)
+        let size = self.element.layout(constraint, ctx);
+        self.needs_layout = false;
+        self.needs_paint = true;
+        size
     }
 
     pub fn after_layout(&mut self, ctx: &mut AfterLayoutContext) {
         self.element.after_layout(ctx);
     }
 
-    pub fn paint(&mut self, origin: Vector2F, ctx: &mut PaintContext) {
-        self.element.paint(origin, ctx);
+    pub fn paint(&mut self, origin: Vector2F, ctx: &mut PaintContext, list: &mut DisplayList) {
+        // A stale box must be re-laid-out before it can paint; painting now
+        // would emit its previous (or zero) layout. Skip rather than abort —
+        // the caller re-runs `layout` and paints again on the next pass.
+        if self.needs_layout {
+            return;
+        }
+        self.element.paint(origin, ctx, list);
+        self.needs_paint = false;
     }
 
     pub fn dispatch_event(&mut self, event: &Event, ctx: &mut EventContext) -> bool {
+        // An unpainted (or invalidated) box occupies no current bounds, so no
+        // event can hit it; report it as unhandled.
+        if self.needs_paint {
+            return false;
+        }
         self.element.dispatch_event(event, ctx)
     }
 
@@ -230,6 +413,25 @@ impl ElementBox {
         self.element.metadata()
     }
 
+    /// Recursively walk this element and its descendants into an
+    /// [`InspectorNode`] tree, capturing each node's name, resolved bounds,
+    /// metadata type, and `debug` JSON. The foundation for a devtools overlay
+    /// and for asserting against the painted layout in tests.
+    pub fn inspect(&self, ctx: &DebugContext) -> InspectorNode {
+        InspectorNode {
+            name: self.name.as_ref().map(|name| name.to_string()),
+            bounds: self.element.bounds(),
+            metadata_type: self.element.metadata().map(|meta| meta.type_id()),
+            debug: self.debug(ctx),
+            children: self
+                .element
+                .children()
+                .iter()
+                .map(|child| child.inspect(ctx))
+                .collect(),
+        }
+    }
+
     pub fn debug(&self, ctx: &DebugContext) -> json::Value {
         let mut value = self.element.debug(ctx);
 
@@ -245,3 +447,185 @@ impl ElementBox {
         value
     }
 }
+
+/// A node in the element-tree snapshot produced by [`ElementBox::inspect`].
+pub struct InspectorNode {
+    /// The `named` label of the element, if it has one.
+    pub name: Option<String>,
+    /// The element's resolved bounds, available once it has been painted.
+    pub bounds: Option<RectF>,
+    /// The `TypeId` of the element's metadata, used to locate elements of a
+    /// given concrete type.
+    pub metadata_type: Option<TypeId>,
+    /// The element's own `debug` serialization.
+    pub debug: serde_json::Value,
+    /// The inspected child nodes, in layout order.
+    pub children: Vec<InspectorNode>,
+}
+
+impl InspectorNode {
+    /// Collect every node in the tree (this one included) whose metadata has
+    /// the given `TypeId`, so tooling can find and highlight all elements of a
+    /// concrete type.
+    pub fn query_type(&self, type_id: TypeId) -> Vec<&InspectorNode> {
+        let mut matches = Vec::new();
+        self.collect_type(type_id, &mut matches);
+        matches
+    }
+
+    fn collect_type<'a>(&'a self, type_id: TypeId, out: &mut Vec<&'a InspectorNode>) {
+        if self.metadata_type == Some(type_id) {
+            out.push(self);
+        }
+        for child in &self.children {
+            child.collect_type(type_id, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::vector::vec2f;
+    use serde_json::json;
+
+    /// A leaf element used to exercise the lifecycle and memo machinery; its
+    /// layout is a fixed size and it reports whatever `cache_key` it was built
+    /// with.
+    struct TestElement {
+        key: Option<u64>,
+    }
+
+    impl Element for TestElement {
+        type LayoutState = ();
+        type PaintState = ();
+
+        fn layout(
+            &mut self,
+            _constraint: SizeConstraint,
+            _ctx: &mut LayoutContext,
+        ) -> (Vector2F, Self::LayoutState) {
+            (vec2f(1.0, 1.0), ())
+        }
+
+        fn after_layout(
+            &mut self,
+            _size: Vector2F,
+            _layout: &mut Self::LayoutState,
+            _ctx: &mut AfterLayoutContext,
+        ) {
+        }
+
+        fn paint(
+            &mut self,
+            _bounds: RectF,
+            _layout: &mut Self::LayoutState,
+            _ctx: &mut PaintContext,
+            _list: &mut DisplayList,
+        ) -> Self::PaintState {
+        }
+
+        fn dispatch_event(
+            &mut self,
+            _event: &Event,
+            _bounds: RectF,
+            _layout: &mut Self::LayoutState,
+            _paint: &mut Self::PaintState,
+            _ctx: &mut EventContext,
+        ) -> bool {
+            false
+        }
+
+        fn cache_key(&self) -> Option<u64> {
+            self.key
+        }
+
+        fn debug(
+            &self,
+            _bounds: RectF,
+            _layout: &Self::LayoutState,
+            _paint: &Self::PaintState,
+            _ctx: &DebugContext,
+        ) -> serde_json::Value {
+            json!({})
+        }
+    }
+
+    fn constraint(max_x: f32, max_y: f32) -> SizeConstraint {
+        SizeConstraint::new(vec2f(0.0, 0.0), vec2f(max_x, max_y))
+    }
+
+    #[test]
+    fn combined_cache_key_is_stable_and_constraint_sensitive() {
+        let element = TestElement { key: Some(7) };
+        let a = Lifecycle::combined_cache_key(&element, constraint(100.0, 100.0));
+        let b = Lifecycle::combined_cache_key(&element, constraint(100.0, 100.0));
+        let c = Lifecycle::combined_cache_key(&element, constraint(50.0, 100.0));
+        assert!(a.is_some());
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn no_cache_key_opts_out_of_memoization() {
+        let element = TestElement { key: None };
+        assert_eq!(
+            Lifecycle::combined_cache_key(&element, constraint(10.0, 10.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn invalidate_drops_the_memoized_key() {
+        let mut lifecycle = Lifecycle::PostLayout {
+            element: TestElement { key: Some(1) },
+            size: vec2f(1.0, 1.0),
+            layout: (),
+            cache_key: Some(42),
+        };
+        lifecycle.invalidate();
+        match lifecycle {
+            Lifecycle::PostLayout { cache_key, .. } => assert_eq!(cache_key, None),
+            _ => panic!("invalidate changed the lifecycle variant"),
+        }
+    }
+
+    #[test]
+    fn size_before_layout_is_zero_rather_than_panicking() {
+        let lifecycle = Lifecycle::Init {
+            element: TestElement { key: None },
+        };
+        assert_eq!(lifecycle.size(), Vector2F::zero());
+    }
+
+    #[test]
+    fn element_box_starts_stale_and_invalidate_marks_it_dirty() {
+        let mut element = TestElement { key: None }.boxed();
+        assert!(element.needs_layout());
+        assert!(element.needs_paint());
+        element.invalidate();
+        assert!(element.needs_layout());
+        assert!(element.needs_paint());
+    }
+
+    #[test]
+    fn query_type_finds_nodes_by_metadata_type() {
+        let leaf = InspectorNode {
+            name: Some("leaf".into()),
+            bounds: None,
+            metadata_type: Some(TypeId::of::<u32>()),
+            debug: json!({}),
+            children: Vec::new(),
+        };
+        let root = InspectorNode {
+            name: None,
+            bounds: None,
+            metadata_type: Some(TypeId::of::<i64>()),
+            debug: json!({}),
+            children: vec![leaf],
+        };
+        assert_eq!(root.query_type(TypeId::of::<u32>()).len(), 1);
+        assert_eq!(root.query_type(TypeId::of::<i64>()).len(), 1);
+        assert_eq!(root.query_type(TypeId::of::<String>()).len(), 0);
+    }
+}